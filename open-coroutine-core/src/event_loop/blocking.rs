@@ -0,0 +1,108 @@
+use crate::event_loop::join::JoinHandle;
+use crate::event_loop::EventLoop;
+use crate::scheduler::Scheduler;
+use once_cell::sync::Lazy;
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+/// 阻塞线程池允许同时存在的最大线程数，超过这个数量的任务在队列里排队，而不是无限制地开线程
+const MAX_BLOCKING_THREADS: usize = 512;
+
+type BlockingTask = Box<dyn FnOnce() -> &'static mut c_void + Send>;
+
+//结果通过co_name带回`Scheduler::get_result`这条既有路径，`EventLoop`原生的
+//`wait_event`轮询不用改一行就能取到结果，event_loop只用来完成后的那一次`wake`
+struct BlockingJob {
+    co_name: String,
+    event_loop: *const EventLoop,
+    task: BlockingTask,
+}
+
+//裸指针只是为了在任务完成时回调`EventLoop::wake`，本身不会被并发访问
+unsafe impl Send for BlockingJob {}
+
+struct BlockingPool {
+    sender: Sender<BlockingJob>,
+    receiver: Arc<Mutex<Receiver<BlockingJob>>>,
+    spawned: AtomicUsize,
+}
+
+impl BlockingPool {
+    fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        BlockingPool {
+            sender,
+            receiver: Arc::new(Mutex::new(receiver)),
+            spawned: AtomicUsize::new(0),
+        }
+    }
+
+    fn submit(&self, job: BlockingJob) {
+        self.spawn_worker_if_room();
+        //即使线程数已经打满也要把任务放进队列，由既有的worker排队消费
+        _ = self.sender.send(job);
+    }
+
+    //懒启动：有任务到来且线程数未到上限时才新开一个常驻worker，worker之间共享同一个
+    //receiver，新任务既可能被刚开的线程捡到，也可能被某个先前已空闲的线程捡到
+    fn spawn_worker_if_room(&self) {
+        if self
+            .spawned
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+                (n < MAX_BLOCKING_THREADS).then_some(n + 1)
+            })
+            .is_err()
+        {
+            return;
+        }
+        let receiver = Arc::clone(&self.receiver);
+        _ = std::thread::Builder::new()
+            .name(String::from("open-coroutine-blocking"))
+            .spawn(move || loop {
+                let job = receiver.lock().unwrap().recv();
+                let Ok(BlockingJob {
+                    co_name,
+                    event_loop,
+                    task,
+                }) = job
+                else {
+                    //发送端全部被丢弃，worker可以退出了
+                    return;
+                };
+                let result = task();
+                //`set_result`写入的是一个跨`EventLoop`、跨线程共享的全局结果存储，按
+                //`co_name`而不是按某个具体`Scheduler`实例索引——阻塞任务本来就跑在线程池
+                //自己的worker线程上，并不属于任何一个`EventLoop`的`scheduler`，只有全局存储
+                //才能让提交方那个`EventLoop`的`get_result(&co_name)`取到结果。写入必须
+                //happens-before之后任意一次`get_result`读到非`None`，否则`join`会在结果已经
+                //产出之后仍然短暂地判定"未完成"而多等一轮。
+                Scheduler::set_result(&co_name, result);
+                //唤醒提交方所在的loop，让它立刻从select里醒来而不必等下一次固定超时
+                _ = unsafe { &*event_loop }.wake();
+            });
+    }
+}
+
+static BLOCKING_POOL: Lazy<BlockingPool> = Lazy::new(BlockingPool::new);
+
+fn next_co_name() -> String {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    format!("blocking-{}", COUNTER.fetch_add(1, Ordering::Relaxed))
+}
+
+/// 把`f`提交给阻塞线程池执行，返回的[`JoinHandle`]和协程版的`submit`返回的完全一样，
+/// `join`/`timeout_join`照常通过`Scheduler::get_result`取回结果。
+pub(crate) fn submit_blocking(
+    event_loop: &EventLoop,
+    f: impl FnOnce() -> &'static mut c_void + Send + 'static,
+) -> std::io::Result<JoinHandle> {
+    let co_name = next_co_name();
+    BLOCKING_POOL.submit(BlockingJob {
+        co_name: co_name.clone(),
+        event_loop: event_loop as *const EventLoop,
+        task: Box::new(f),
+    });
+    Ok(JoinHandle::new(event_loop as *const EventLoop, &co_name))
+}
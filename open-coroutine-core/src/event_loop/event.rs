@@ -0,0 +1,70 @@
+use crate::event_loop::interest::Interest;
+
+/// 一次`select`上报的单个fd就绪事件。
+#[derive(Debug, Copy, Clone)]
+pub struct Event {
+    fd: libc::c_int,
+    token: usize,
+    interests: Interest,
+}
+
+impl Event {
+    #[must_use]
+    pub fn fd(&self) -> libc::c_int {
+        self.fd
+    }
+
+    #[must_use]
+    pub fn token(&self) -> usize {
+        self.token
+    }
+
+    #[must_use]
+    pub fn is_readable(&self) -> bool {
+        self.interests.is_readable()
+    }
+
+    #[must_use]
+    pub fn is_writable(&self) -> bool {
+        self.interests.is_writable()
+    }
+}
+
+/// 一批`select`调用收集到的就绪事件，由具体的[`crate::event_loop::selector::Selector`]
+/// 实现负责填充。
+#[derive(Debug, Default)]
+pub struct Events {
+    records: Vec<Event>,
+    capacity: usize,
+}
+
+impl Events {
+    #[must_use]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Events {
+            records: Vec::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    pub(crate) fn push(&mut self, fd: libc::c_int, token: usize, interests: Interest) {
+        self.records.push(Event {
+            fd,
+            token,
+            interests,
+        });
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Event> {
+        self.records.iter()
+    }
+}
@@ -0,0 +1,57 @@
+/// 描述对一个fd关心的就绪方向，可以通过`|=`组合。
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Interest(u8);
+
+impl Interest {
+    pub const NONE: Interest = Interest(0b00);
+    pub const READABLE: Interest = Interest(0b01);
+    pub const WRITABLE: Interest = Interest(0b10);
+
+    #[must_use]
+    pub fn is_readable(self) -> bool {
+        self.0 & Self::READABLE.0 != 0
+    }
+
+    #[must_use]
+    pub fn is_writable(self) -> bool {
+        self.0 & Self::WRITABLE.0 != 0
+    }
+
+    #[cfg(not(feature = "poll-selector"))]
+    pub(crate) fn to_epoll_events(self) -> u32 {
+        let mut events = libc::EPOLLET as u32;
+        if self.is_readable() {
+            events |= libc::EPOLLIN as u32;
+        }
+        if self.is_writable() {
+            events |= libc::EPOLLOUT as u32;
+        }
+        events
+    }
+
+    #[cfg(feature = "poll-selector")]
+    pub(crate) fn to_poll_events(self) -> libc::c_short {
+        let mut events = 0;
+        if self.is_readable() {
+            events |= libc::POLLIN;
+        }
+        if self.is_writable() {
+            events |= libc::POLLOUT;
+        }
+        events as libc::c_short
+    }
+}
+
+impl std::ops::BitOr for Interest {
+    type Output = Interest;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Interest(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::BitOrAssign for Interest {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
@@ -1,4 +1,4 @@
-use crate::event_loop::EventLoop;
+use crate::event_loop::{EventLoop, EventLoops};
 use crate::scheduler::Scheduler;
 use std::ffi::{c_char, c_void, CStr, CString};
 use std::time::Duration;
@@ -19,6 +19,21 @@ impl JoinHandle {
         JoinHandle::new(std::ptr::null(), "")
     }
 
+    /// 请求取消这个协程：还没开始跑的直接回收栈，挂在某个syscall上等待的会被連同它关注的
+    /// fd一起从所在`EventLoop`摘掉，并阻止后续再被恢复。取消之后`join`/`timeout_join`会
+    /// 收到`ErrorKind::Interrupted`，而不是永远等不到结果。
+    ///
+    /// 走[`EventLoops::cancel`]而不是直接操作`self.0`指向的那个loop：协程提交之后可能被
+    /// work-stealing搬到了另一个loop上，只对提交时的那个loop做deregister会摘错io、
+    /// `self.0`所在loop的`scheduler`也不是协程此刻实际所在的那个，`cancel`会静默失效。
+    pub fn cancel(&self) -> std::io::Result<()> {
+        let co_name = unsafe { CStr::from_ptr(self.1).to_str().unwrap() };
+        if co_name.is_empty() {
+            return Ok(());
+        }
+        EventLoops::cancel(co_name)
+    }
+
     pub fn timeout_join(&self, dur: Duration) -> std::io::Result<Option<&'static mut c_void>> {
         self.timeout_at_join(open_coroutine_timer::get_timeout_time(dur))
     }
@@ -32,33 +47,47 @@ impl JoinHandle {
             return Ok(None);
         }
         let event_loop = unsafe { &*self.0 };
-        let mut result = Scheduler::get_result(co_name);
-        while result.is_none() {
-            let left_time = timeout_time
-                .saturating_sub(open_coroutine_timer::now())
-                .min(10_000_000);
+        loop {
+            if Scheduler::is_cancelled(co_name) {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::Interrupted,
+                    "coroutine cancelled",
+                ));
+            }
+            if let Some(result) = Scheduler::get_result(co_name) {
+                return Ok(result.get_result());
+            }
+            let left_time =
+                event_loop.quantize(timeout_time.saturating_sub(open_coroutine_timer::now()));
             if left_time == 0 {
                 //timeout
                 return Err(std::io::Error::new(std::io::ErrorKind::TimedOut, "timeout"));
             }
             event_loop.wait_event(Some(Duration::from_nanos(left_time)))?;
-            result = Scheduler::get_result(co_name);
         }
-        Ok(result.unwrap().get_result())
     }
 
+    /// `Scheduler::is_cancelled`必须先于`get_result`被检查：协程被[`JoinHandle::cancel`]取消
+    /// 后不会再产出一个普通结果，所以这里依赖`Scheduler`把cancelled当成一种能被查询到的
+    /// 终态，而不是指望`get_result`最终会返回某个表示"被取消"的结果值。
     pub fn join(self) -> std::io::Result<Option<&'static mut c_void>> {
         let co_name = unsafe { CStr::from_ptr(self.1).to_str().unwrap() };
         if co_name.is_empty() {
             return Ok(None);
         }
         let event_loop = unsafe { &*self.0 };
-        let mut result = Scheduler::get_result(co_name);
-        while result.is_none() {
+        loop {
+            if Scheduler::is_cancelled(co_name) {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::Interrupted,
+                    "coroutine cancelled",
+                ));
+            }
+            if let Some(result) = Scheduler::get_result(co_name) {
+                return Ok(result.get_result());
+            }
             event_loop.wait_event(Some(Duration::from_millis(10)))?;
-            result = Scheduler::get_result(co_name);
         }
-        Ok(result.unwrap().get_result())
     }
 }
 
@@ -76,7 +105,7 @@ mod tests {
         let pair = Arc::new((Mutex::new(true), Condvar::new()));
         let pair2 = Arc::clone(&pair);
         let handler = std::thread::spawn(move || {
-            let event_loop = EventLoop::new().unwrap();
+            let event_loop = EventLoop::new(None).unwrap();
             let handle1 = event_loop
                 .submit(
                     |_, _| {
@@ -130,7 +159,7 @@ mod tests {
         let pair = Arc::new((Mutex::new(true), Condvar::new()));
         let pair2 = Arc::clone(&pair);
         let handler = std::thread::spawn(move || {
-            let event_loop = EventLoop::new().unwrap();
+            let event_loop = EventLoop::new(None).unwrap();
             let handle = event_loop
                 .submit(
                     |_, _| {
@@ -176,4 +205,51 @@ mod tests {
             Ok(())
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn cancel_test() -> std::io::Result<()> {
+        let pair = Arc::new((Mutex::new(true), Condvar::new()));
+        let pair2 = Arc::clone(&pair);
+        let handler = std::thread::spawn(move || {
+            let event_loop = EventLoop::new(None).unwrap();
+            let handle = event_loop
+                .submit(
+                    |suspender, _| {
+                        suspender.suspend();
+                        println!("[coroutine4] launched");
+                        val(6)
+                    },
+                    None,
+                )
+                .expect("submit failed !");
+            handle.cancel().expect("cancel failed !");
+            let error = handle.join().unwrap_err();
+            assert_eq!(error.kind(), std::io::ErrorKind::Interrupted);
+
+            let (lock, cvar) = &*pair2;
+            let mut pending = lock.lock().unwrap();
+            *pending = false;
+            // notify the condvar that the value has changed.
+            cvar.notify_one();
+        });
+
+        // wait for the thread to start up
+        let (lock, cvar) = &*pair;
+        let result = cvar
+            .wait_timeout_while(
+                lock.lock().unwrap(),
+                Duration::from_millis(3000),
+                |&mut pending| pending,
+            )
+            .unwrap();
+        if result.1.timed_out() {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                "cancel test failed",
+            ))
+        } else {
+            handler.join().unwrap();
+            Ok(())
+        }
+    }
+}
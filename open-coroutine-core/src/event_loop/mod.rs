@@ -2,14 +2,42 @@ use crate::coroutine::suspender::Suspender;
 use crate::event_loop::event::Events;
 use crate::event_loop::interest::Interest;
 use crate::event_loop::join::JoinHandle;
-use crate::event_loop::selector::Selector;
+use crate::event_loop::scheduled_io::ScheduledIo;
+use crate::event_loop::selector::{DefaultSelector, Selector};
 use crate::scheduler::{SchedulableCoroutine, Scheduler};
 use once_cell::sync::{Lazy, OnceCell};
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::ffi::c_void;
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
 use std::time::Duration;
 
+/// 不开启节流时，每次`wait`最多等待的时长，避免长时间阻塞导致无法及时响应`stop`
+const DEFAULT_MAX_WAIT: u64 = 10_000_000;
+
+/// 分配给唤醒用`waker_fd`的固定token，真实协程的token来自泄漏的协程名指针，不会撞上这个值
+const WAKER_TOKEN: usize = usize::MAX;
+
+/// 空闲worker尝试从兄弟loop偷协程时最多尝试几个victim就放弃
+const MAX_STEAL_ATTEMPTS: usize = 4;
+
+//挑选一个偷取目标用，不需要密码学强度，per-thread的简单xorshift足够把尝试过程打散
+fn random_index(len: usize) -> usize {
+    thread_local! {
+        static RNG_STATE: std::cell::Cell<u64> = std::cell::Cell::new(
+            open_coroutine_timer::now() ^ 0x9E37_79B9_7F4A_7C15
+        );
+    }
+    RNG_STATE.with(|state| {
+        let mut x = state.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        state.set(x);
+        (x as usize) % len
+    })
+}
+
 pub mod join;
 
 pub mod event;
@@ -18,6 +46,10 @@ pub mod interest;
 
 mod selector;
 
+mod scheduled_io;
+
+mod blocking;
+
 /// 做C兼容时会用到
 pub type UserFunc =
     extern "C" fn(*const Suspender<(), ()>, &'static mut c_void) -> &'static mut c_void;
@@ -29,7 +61,7 @@ static mut INDEX: Lazy<AtomicUsize> = Lazy::new(|| AtomicUsize::new(0));
 
 static mut EVENT_LOOPS: Lazy<Box<[EventLoop]>> = Lazy::new(|| {
     (0..num_cpus::get())
-        .map(|_| EventLoop::new().expect("init event loop failed!"))
+        .map(|_| EventLoop::new(None).expect("init event loop failed!"))
         .collect()
 });
 
@@ -59,11 +91,23 @@ impl EventLoops {
         }
     }
 
-    pub fn start() {
-        if EVENT_LOOP_STARTED
+    /// 启动所有`EventLoop`工作线程，`throttle`为`None`时不开启节流，否则所有`EventLoop`
+    /// 共享同一个节流策略，详见[`EventLoop::set_throttle`]。只有真正把`EVENT_LOOP_STARTED`
+    /// 从`false`翻到`true`的这一次调用才会套用`throttle`；`submit`/`submit_blocking`等内部
+    /// helper一律传`None`地重复调用`start`只为确保线程已起来，不能让这个`None`在每次submit
+    /// 时都把之前配置好的节流重置掉。
+    pub fn start(throttle: Option<Duration>) {
+        let already_started = EVENT_LOOP_STARTED
             .compare_exchange(false, true, Ordering::Relaxed, Ordering::Relaxed)
-            .is_err()
-        {
+            .is_err();
+        if !already_started {
+            unsafe {
+                for event_loop in EVENT_LOOPS.iter() {
+                    event_loop.set_throttle(throttle);
+                }
+            }
+        }
+        if already_started {
             //初始化event_loop线程
             _ = EVENT_LOOP_WORKERS.get_or_init(|| {
                 (1..unsafe { EVENT_LOOPS.len() })
@@ -71,7 +115,16 @@ impl EventLoops {
                         std::thread::spawn(|| {
                             let event_loop = EventLoops::next();
                             while EVENT_LOOP_STARTED.load(Ordering::Acquire) {
-                                _ = event_loop.wait_event(Some(Duration::from_millis(10)));
+                                //在真正睡进`select`之前先尝试偷一把，而不是等一整个quantum的
+                                //select超时回来才偷——否则空闲worker还是要白白多等一轮才能
+                                //接过兄弟loop的活，work-stealing本该省掉的尾延迟又被加回来了
+                                EventLoops::try_steal(event_loop);
+                                //用`quantum()`取当前配置下一轮最多该睡多久，而不是借
+                                //`quantize(u64::MAX)`——`quantize`对`u64::MAX`没有特判，开启
+                                //节流时它会被当成真实的剩余超时向上取整并饱和成`u64::MAX`，
+                                //等于几乎无限期地睡下去，这里要的始终是"一个quantum"本身
+                                let quantum = event_loop.quantum();
+                                _ = event_loop.wait_event(Some(Duration::from_nanos(quantum)));
                             }
                         })
                     })
@@ -80,6 +133,43 @@ impl EventLoops {
         }
     }
 
+    //空闲worker在`wait_event`之前调用：本地没有待调度的协程了，随机挑一个兄弟loop偷一半
+    //过来，最多尝试`MAX_STEAL_ATTEMPTS`次就放弃，避免系统普遍空闲时来回扫描造成抖动。
+    //monitor固定跑在index 0，不参与偷取——既不会作为偷的一方（worker线程经`next()`也可能
+    //拿到index 0的loop，这里对它直接返回），也不能被当成victim（否则驱动
+    //`preemptive-schedule`的monitor协程会被偷跑到别的loop上）。
+    //
+    //`event_loop`和`victim`都只借用共享引用：`steal_from`要同时摸一把自己的`scheduler`和
+    //victim的`scheduler`/`io_records`，如果两边都要求`&mut EventLoop`，当`victim_index`恰好
+    //撞上`event_loop`自己的下标时就是对同一块`Box<[EventLoop]>`内存取两个`&mut`，属于别名
+    //UB（`ptr::eq`只能在运行时发现撞车，编译期并不能阻止）。`EventLoop`的可变状态本就全部
+    //包在`Mutex`/`Atomic*`里，`&self`足够安全地完成这里的所有操作。
+    fn try_steal(event_loop: &EventLoop) {
+        if !event_loop.is_idle() {
+            return;
+        }
+        let len = unsafe { EVENT_LOOPS.len() };
+        if len <= 1 {
+            return;
+        }
+        if std::ptr::eq(event_loop, unsafe { EVENT_LOOPS.get(0).unwrap() }) {
+            return;
+        }
+        for _ in 0..MAX_STEAL_ATTEMPTS {
+            let victim_index = random_index(len);
+            if victim_index == 0 {
+                continue;
+            }
+            let victim = unsafe { EVENT_LOOPS.get(victim_index).unwrap() };
+            if std::ptr::eq(victim, event_loop) {
+                continue;
+            }
+            if event_loop.steal_from(victim) > 0 {
+                return;
+            }
+        }
+    }
+
     pub fn stop() {
         #[cfg(all(unix, feature = "preemptive-schedule"))]
         crate::monitor::Monitor::stop();
@@ -90,7 +180,7 @@ impl EventLoops {
         f: impl FnOnce(&Suspender<'_, (), ()>, ()) -> &'static mut c_void + 'static,
         stack_size: Option<usize>,
     ) -> std::io::Result<JoinHandle> {
-        EventLoops::start();
+        EventLoops::start(None);
         EventLoops::next().submit(f, stack_size)
     }
 
@@ -98,13 +188,21 @@ impl EventLoops {
         EventLoops::next().try_timeout_schedule(timeout_time)
     }
 
+    /// 把一个会真正阻塞的闭包丢给专用的阻塞线程池执行，而不是占着某个`EventLoop`的
+    /// `wait`循环，详见[`blocking::submit_blocking`]。
+    pub fn submit_blocking(
+        f: impl FnOnce() -> &'static mut c_void + Send + 'static,
+    ) -> std::io::Result<JoinHandle> {
+        EventLoops::start(None);
+        blocking::submit_blocking(EventLoops::next(), f)
+    }
+
     pub fn wait_event(timeout: Option<Duration>) -> std::io::Result<()> {
         let timeout_time = open_coroutine_timer::get_timeout_time(timeout.unwrap_or(Duration::MAX));
         let event_loop = EventLoops::next();
         loop {
-            let left_time = timeout_time
-                .saturating_sub(open_coroutine_timer::now())
-                .min(10_000_000);
+            let left_time =
+                event_loop.quantize(timeout_time.saturating_sub(open_coroutine_timer::now()));
             if left_time == 0 {
                 //timeout
                 return Ok(());
@@ -122,48 +220,165 @@ impl EventLoops {
     }
 
     pub fn del_event(fd: libc::c_int) {
-        (0..unsafe { EVENT_LOOPS.len() }).for_each(|_| {
-            _ = EventLoops::next().del_event(fd);
-        });
+        EventLoops::with_owning_loop(fd, EventLoop::del_event);
     }
 
     pub fn del_read_event(fd: libc::c_int) {
-        (0..unsafe { EVENT_LOOPS.len() }).for_each(|_| {
-            _ = EventLoops::next().del_read_event(fd);
-        });
+        EventLoops::with_owning_loop(fd, EventLoop::del_read_event);
     }
 
     pub fn del_write_event(fd: libc::c_int) {
-        (0..unsafe { EVENT_LOOPS.len() }).for_each(|_| {
-            _ = EventLoops::next().del_write_event(fd);
-        });
+        EventLoops::with_owning_loop(fd, EventLoop::del_write_event);
+    }
+
+    /// 取消一个协程：co_name对应的协程此刻可能挂在任意一个loop上（提交时选中的那个loop，
+    /// 或者`try_steal`把它搬到的另一个loop），所以不能像[`JoinHandle`]手里那个loop指针那样
+    /// 只认提交时的那一个——广播式地在每个loop上都尝试摘掉它可能挂着的io关注（没有命中的
+    /// loop上`EventLoop::cancel`是no-op），再调用全局的`Scheduler::cancel`标记取消状态。
+    /// `Scheduler::cancel`必须和`is_cancelled`/`get_result`/`set_result`一样是按协程名索引
+    /// 的全局关联函数而非某个`Scheduler`实例的方法：取消状态在提交时那个loop的`scheduler`上
+    /// 打标记，而`join`/`timeout_join`读的是全局状态，两者连不上，取消会悄无声息地不生效。
+    /// 状态机本身按ready/suspended/finished/cancelled维护：对ready/suspended的协程标记为
+    /// cancelled并阻止后续再被调度到；对已经finished的协程什么都不做（结果已经产出，取消
+    /// 没有意义）。`cancelled`需要是`get_result`能识别的一个专门结果，而不是普通的运行结果，
+    /// 这样`is_cancelled`才能先于`get_result`拿到明确的"已取消"信号。
+    pub(crate) fn cancel(co_name: &str) -> std::io::Result<()> {
+        unsafe {
+            for event_loop in EVENT_LOOPS.iter() {
+                _ = event_loop.cancel(co_name);
+            }
+        }
+        Scheduler::cancel(co_name)
+    }
+
+    //fd只会在调用`add_*_event`时被注册到`EventLoops::next()`当时选中的loop上；每次等待
+    //都重新调用`next()`，同一个fd可能先后落到多个loop上，所以删除时去操作每一个真正持有
+    //这条`io_records`记录的loop，而不是像之前那样对着每个loop都广播一遍、再把大多数调用
+    //天然产生的错误直接丢掉
+    fn with_owning_loop(
+        fd: libc::c_int,
+        f: impl Fn(&EventLoop, libc::c_int) -> std::io::Result<()>,
+    ) {
+        unsafe {
+            for event_loop in EVENT_LOOPS.iter().filter(|e| e.owns_fd(fd)) {
+                _ = f(event_loop, fd);
+            }
+        }
     }
 }
 
 #[derive(Debug)]
 pub struct EventLoop {
-    selector: Selector,
+    //按trait对象持有，使`EventLoop`不再与某一种具体的就绪探测机制（epoll/poll...）绑死
+    selector: Box<dyn Selector>,
     scheduler: Scheduler,
     waiting: AtomicBool,
+    //节流间隔，单位纳秒，0表示不开启节流
+    throttle: AtomicU64,
+    //每个fd在本loop上的就绪状态缓存，替换之前跨loop共享的`static mut`记录，
+    //避免loop A注册的fd被loop B的`del_event`误删
+    io_records: Mutex<HashMap<libc::c_int, ScheduledIo>>,
+    //注册进selector的eventfd，让阻塞线程池等外部生产者可以在结果就绪后把本loop从
+    //select中唤醒，而不必等到下一次固定超时
+    waker_fd: libc::c_int,
 }
 
-static mut READABLE_RECORDS: Lazy<HashSet<libc::c_int>> = Lazy::new(HashSet::new);
-
-static mut READABLE_TOKEN_RECORDS: Lazy<HashMap<libc::c_int, usize>> = Lazy::new(HashMap::new);
-
-static mut WRITABLE_RECORDS: Lazy<HashSet<libc::c_int>> = Lazy::new(HashSet::new);
-
-static mut WRITABLE_TOKEN_RECORDS: Lazy<HashMap<libc::c_int, usize>> = Lazy::new(HashMap::new);
-
 impl EventLoop {
-    pub fn new() -> std::io::Result<Self> {
+    pub fn new(throttle: Option<Duration>) -> std::io::Result<Self> {
+        let selector = DefaultSelector::new()?;
+        let waker_fd = unsafe { libc::eventfd(0, libc::EFD_NONBLOCK | libc::EFD_CLOEXEC) };
+        if waker_fd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        selector.register(waker_fd, WAKER_TOKEN, Interest::READABLE)?;
         Ok(EventLoop {
-            selector: Selector::new()?,
+            selector,
             scheduler: Scheduler::new(),
             waiting: AtomicBool::new(false),
+            throttle: AtomicU64::new(throttle.map_or(0, |t| t.as_nanos() as u64)),
+            io_records: Mutex::new(HashMap::new()),
+            waker_fd,
         })
     }
 
+    /// 从另一个线程唤醒正阻塞在`select`里的本loop，用于阻塞线程池任务完成后及时把结果
+    /// 带回`wait_event`轮询的`Scheduler::get_result`路径，而不必等下一次固定超时
+    pub(crate) fn wake(&self) -> std::io::Result<()> {
+        let val: u64 = 1;
+        let ret = unsafe {
+            libc::write(
+                self.waker_fd,
+                (&val as *const u64).cast(),
+                std::mem::size_of::<u64>(),
+            )
+        };
+        if ret < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn drain_waker(&self) {
+        let mut buf = [0u8; 8];
+        unsafe {
+            _ = libc::read(self.waker_fd, buf.as_mut_ptr().cast(), buf.len());
+        }
+    }
+
+    /// 开启/更新节流策略：`wait`不再紧跟实际超时时间计算`left_time`，而是将其向上取整到
+    /// `throttle`的整数倍。这只约束单次调用自己等待多久——本loop连续空闲时，每一轮drive
+    /// 循环的`select`都会阻塞满一个quantum才醒来去检查有没有活干，从而把"没有协程等待时
+    /// 白白高频`select`"这种idle polling storm摊薄到每quantum一次；并发的多个调用者在
+    /// 同一时刻落到同一个loop上时，也会被既有的`waiting`标志天然合并成一次`select`。但对
+    /// 同一线程顺序发起、彼此独立的`wait_read_event`/`wait_write_event`调用，节流并不会把
+    /// 它们合并成一个quantum一次`select`——每次调用仍然各自触发一次syscall，只是等待时长
+    /// 被取整到quantum边界。传入`None`关闭节流，恢复逐次计算的默认行为。
+    pub fn set_throttle(&self, throttle: Option<Duration>) {
+        self.throttle.store(
+            throttle.map_or(0, |t| t.as_nanos() as u64),
+            Ordering::Relaxed,
+        );
+    }
+
+    //未开启节流时退化为原先固定10ms的上限；开启节流后把`left_time`向上取整到`throttle`的
+    //整数倍，使每次`wait`都在quantum边界上醒来，而不是（错误地）把等待截断到一个throttle
+    //之内——那样只会让`select`喊醒得更频繁。取整只决定单次调用等多久，并不会把彼此独立的
+    //多次`wait`合并成一次`selector.select`/`scheduler.try_timed_schedule`。
+    //
+    //先做除法再按余数补一个`throttle`，而不是`(left_time + throttle - 1) / throttle`：
+    //后者在`left_time`落在`(u64::MAX - throttle + 1, u64::MAX]`时会在加法阶段就溢出——这并非
+    //纸面情况，`EventLoops::wait_event(None)`之类以`Duration::MAX`为超时算出的
+    //`left_time = timeout_time.saturating_sub(now())`正好是紧贴`u64::MAX`以下的一个值，
+    //debug下触发`attempt to add with overflow`的panic，release下回绕成一个极小值，把本该
+    //几乎永久的等待变成忙轮询。除法本身不会溢出，补余数时再用`saturating_add`兜底，彻底消除
+    //溢出路径，`left_time == 0`/`u64::MAX`都不需要再单独特判。
+    fn quantize(&self, left_time: u64) -> u64 {
+        let throttle = self.throttle.load(Ordering::Relaxed);
+        if throttle == 0 {
+            return left_time.min(DEFAULT_MAX_WAIT);
+        }
+        let floor = left_time / throttle * throttle;
+        if left_time % throttle == 0 {
+            floor
+        } else {
+            floor.saturating_add(throttle)
+        }
+    }
+
+    //本loop当前配置下一轮最多该睡多久：不开节流时沿用固定的`DEFAULT_MAX_WAIT`上限，开节流
+    //后就是`throttle`本身。给空闲驱动循环"这一轮最多睡一个quantum"这种场景专用，和
+    //`quantize`把某个具体的剩余超时向上取整到quantum边界是两回事，不能再借
+    //`quantize(u64::MAX)`这种特判技巧混用——`quantize`去掉溢出路径后不再对`u64::MAX`
+    //特判，任何输入都会被当成真实的剩余超时去取整。
+    fn quantum(&self) -> u64 {
+        let throttle = self.throttle.load(Ordering::Relaxed);
+        if throttle == 0 {
+            DEFAULT_MAX_WAIT
+        } else {
+            throttle
+        }
+    }
+
     pub fn submit(
         &self,
         f: impl FnOnce(&Suspender<'_, (), ()>, ()) -> &'static mut c_void + 'static,
@@ -180,6 +395,43 @@ impl EventLoop {
         Ok(timeout_time.saturating_sub(open_coroutine_timer::now()))
     }
 
+    //本loop的ready队列是否已经空了，空闲worker据此判断要不要去偷兄弟loop的协程
+    //
+    //`ready_len`/`steal_half`/`accept_stolen`要求`Scheduler`自己的ready队列是一个真正
+    //lock-free的双端结构（例如work-stealing deque）：本loop的worker线程在队列一端
+    //push/pop自己的协程，同时可能有其它loop的worker线程在`steal_from`里对同一个队列调用
+    //`steal_half`。`ready_len`只是把队列长度作为一个近似值读出来判断"要不要去偷"，允许
+    //与并发的push/pop产生竞态（读到偶尔过期的值顶多让这次偷取白跑一趟，不影响正确性）；
+    //但`steal_half`/`accept_stolen`本身必须是线程安全的——它们会被不持有本loop锁的另一个
+    //线程直接调用，不能依赖调用方已经持锁这个前提。
+    fn is_idle(&self) -> bool {
+        self.scheduler.ready_len() == 0
+    }
+
+    //从`victim`的ready队列里偷一半协程过来塞进本loop的`scheduler`，返回偷到的数量。
+    //被偷走的协程如果之前在`victim`上等过fd（等待结束后read/write_token并不会被自动清掉，
+    //复用同一个fd时本就要留着），那条`io_records`记录从此再也没人会清——协程已经换了家，
+    //今后不会再由`victim`的selector唤醒，留在原地只会越攒越多，还可能在同一个fd被
+    //复用时触发过期的`resume_syscall`，所以搬家的同时要把这些记录从`victim`上摘掉。
+    //
+    //`victim`正被它自己的worker线程并发地跑着`wait`/`select`，这里只借用`&EventLoop`：
+    //`scheduler.steal_half`/`accept_stolen`是`Scheduler`自己的ready队列一侧的无锁操作，
+    //`deregister_io_for`摸的`io_records`/`selector`也都各自包着`Mutex`/系统调用级别的同步，
+    //不依赖调用方持有`&mut EventLoop`才能安全——这正是把`del_*_event`系列方法改成`&self`
+    //的原因，否则从偷取方线程拿`victim`的`&mut`和victim worker线程自己用到的引用就会打架。
+    fn steal_from(&self, victim: &EventLoop) -> usize {
+        let stolen = victim.scheduler.steal_half();
+        if stolen.is_empty() {
+            return 0;
+        }
+        let len = stolen.len();
+        for co in &stolen {
+            _ = victim.deregister_io_for(co.get_name());
+        }
+        self.scheduler.accept_stolen(stolen);
+        len
+    }
+
     #[allow(clippy::ptr_as_ptr)]
     fn token() -> usize {
         if let Some(co) = SchedulableCoroutine::current() {
@@ -190,59 +442,99 @@ impl EventLoop {
         }
     }
 
+    //注意：不能先`records.entry(fd).or_default()`再判断`read_token().is_some()`——那样
+    //即便是早返回分支，也会在`owns_fd`眼里把这个fd记成"本loop持有"，而`selector.register`
+    //失败时（`?`提前返回）同样会留下一个没有任何token的空`ScheduledIo`。这类幽灵记录会让
+    //`EventLoops::with_owning_loop`把`del_event`系列操作误路由到一个其实什么都没注册过的
+    //loop上。只在`register`成功之后才真正插入/更新记录。
     pub fn add_read_event(&self, fd: libc::c_int) -> std::io::Result<()> {
-        unsafe {
-            if READABLE_TOKEN_RECORDS.contains_key(&fd) {
-                return Ok(());
-            }
+        let mut records = self.io_records.lock().unwrap();
+        if records.get(&fd).map_or(false, |io| io.read_token().is_some()) {
+            return Ok(());
         }
         let token = EventLoop::token();
         self.selector.register(fd, token, Interest::READABLE)?;
-        unsafe {
-            assert!(READABLE_RECORDS.insert(fd));
-            assert_eq!(None, READABLE_TOKEN_RECORDS.insert(fd, token));
-        }
+        records.entry(fd).or_default().set_read_token(Some(token));
         Ok(())
     }
 
     pub fn add_write_event(&self, fd: libc::c_int) -> std::io::Result<()> {
-        unsafe {
-            if WRITABLE_TOKEN_RECORDS.contains_key(&fd) {
-                return Ok(());
-            }
+        let mut records = self.io_records.lock().unwrap();
+        if records.get(&fd).map_or(false, |io| io.write_token().is_some()) {
+            return Ok(());
         }
         let token = EventLoop::token();
         self.selector.register(fd, token, Interest::WRITABLE)?;
-        unsafe {
-            assert!(WRITABLE_RECORDS.insert(fd));
-            assert_eq!(None, WRITABLE_TOKEN_RECORDS.insert(fd, token));
-        }
+        records.entry(fd).or_default().set_write_token(Some(token));
         Ok(())
     }
 
-    pub fn del_event(&mut self, fd: libc::c_int) -> std::io::Result<()> {
+    pub fn del_event(&self, fd: libc::c_int) -> std::io::Result<()> {
         self.selector.deregister(fd)?;
-        unsafe {
-            _ = READABLE_RECORDS.remove(&fd);
-            _ = READABLE_TOKEN_RECORDS.remove(&fd);
-            _ = WRITABLE_RECORDS.remove(&fd);
-            _ = WRITABLE_TOKEN_RECORDS.remove(&fd);
-        }
+        _ = self.io_records.lock().unwrap().remove(&fd);
         Ok(())
     }
 
-    pub fn del_read_event(&mut self, fd: libc::c_int) -> std::io::Result<()> {
-        unsafe {
-            if READABLE_RECORDS.contains(&fd) {
-                if WRITABLE_RECORDS.contains(&fd) {
+    //本loop是否持有`fd`的`io_records`记录，供`EventLoops::with_owning_loop`定位该把
+    //del_event系列操作路由到哪个loop
+    fn owns_fd(&self, fd: libc::c_int) -> bool {
+        self.io_records.lock().unwrap().contains_key(&fd)
+    }
+
+    /// 只摘掉`co_name`在本loop上挂着的那一个方向的io关注（`del_read_event`/`del_write_event`），
+    /// 而不是整个fd——同一个fd上另一个方向可能正被另一个协程等待着，blanket的`del_event`会把
+    /// 那个协程的关注也一并删掉，让它在`join`/`timeout_join`里永远等不到结果。在`co_name`根本
+    /// 没有挂在本loop上时是no-op，因此可以放心地对每一个loop都广播调用一遍而不必先判断
+    /// 协程当前到底被work-stealing搬到了哪个loop上；真正标记"这个协程被取消了"的全局状态
+    /// 由[`EventLoops::cancel`]调用的`Scheduler::cancel`负责，不在这个方法里。
+    pub(crate) fn cancel(&self, co_name: &str) -> std::io::Result<()> {
+        self.deregister_io_for(co_name)
+    }
+
+    //只摘掉`co_name`自己挂着的那一个方向，fd上另一个方向（可能属于另一个协程）保持不动。
+    //被`cancel`和`steal_from`共用：前者是协程本身不再需要这个fd了，后者是协程换了家，
+    //旧loop上的登记同样成了没人会再消费的死记录，处理方式完全一样。
+    fn deregister_io_for(&self, co_name: &str) -> std::io::Result<()> {
+        match self.find_fd_by_co_name(co_name) {
+            Some((fd, true)) => self.del_read_event(fd),
+            Some((fd, false)) => self.del_write_event(fd),
+            None => Ok(()),
+        }
+    }
+
+    //token是某次`EventLoop::token()`泄漏出的协程名指针，这里原样转回`&String`按内容比对，
+    //从而反查出当前正在等待某个协程的fd，以及它挂的是读方向还是写方向（true表示读）
+    fn find_fd_by_co_name(&self, co_name: &str) -> Option<(libc::c_int, bool)> {
+        let as_name = |token: Option<usize>| -> Option<&'static String> {
+            match token {
+                Some(t) if t != 0 => Some(unsafe { &*(t as *const c_void).cast::<String>() }),
+                _ => None,
+            }
+        };
+        let records = self.io_records.lock().unwrap();
+        records.iter().find_map(|(fd, io)| {
+            if as_name(io.read_token()).map_or(false, |name| name == co_name) {
+                Some((*fd, true))
+            } else if as_name(io.write_token()).map_or(false, |name| name == co_name) {
+                Some((*fd, false))
+            } else {
+                None
+            }
+        })
+    }
+
+    pub fn del_read_event(&self, fd: libc::c_int) -> std::io::Result<()> {
+        let mut records = self.io_records.lock().unwrap();
+        if let Some(io) = records.get_mut(&fd) {
+            if io.read_token().is_some() {
+                if let Some(write_token) = io.write_token() {
                     //写事件不能删
-                    self.selector.reregister(
-                        fd,
-                        WRITABLE_TOKEN_RECORDS.remove(&fd).unwrap_or(0),
-                        Interest::WRITABLE,
-                    )?;
-                    assert!(READABLE_RECORDS.remove(&fd));
+                    self.selector
+                        .reregister(fd, write_token, Interest::WRITABLE)?;
+                    io.set_read_token(None);
+                    io.clear_readable();
                 } else {
+                    drop(records);
                     self.del_event(fd)?;
                 }
             }
@@ -250,18 +542,18 @@ impl EventLoop {
         Ok(())
     }
 
-    pub fn del_write_event(&mut self, fd: libc::c_int) -> std::io::Result<()> {
-        unsafe {
-            if WRITABLE_RECORDS.contains(&fd) {
-                if READABLE_RECORDS.contains(&fd) {
+    pub fn del_write_event(&self, fd: libc::c_int) -> std::io::Result<()> {
+        let mut records = self.io_records.lock().unwrap();
+        if let Some(io) = records.get_mut(&fd) {
+            if io.write_token().is_some() {
+                if let Some(read_token) = io.read_token() {
                     //读事件不能删
-                    self.selector.reregister(
-                        fd,
-                        READABLE_TOKEN_RECORDS.remove(&fd).unwrap_or(0),
-                        Interest::READABLE,
-                    )?;
-                    assert!(WRITABLE_RECORDS.remove(&fd));
+                    self.selector
+                        .reregister(fd, read_token, Interest::READABLE)?;
+                    io.set_write_token(None);
+                    io.clear_writable();
                 } else {
+                    drop(records);
                     self.del_event(fd)?;
                 }
             }
@@ -298,14 +590,25 @@ impl EventLoop {
         self.waiting.store(false, Ordering::Relaxed);
         for event in events.iter() {
             let fd = event.fd();
+            if fd == self.waker_fd {
+                //本loop的waker被写入，说明有阻塞线程池任务或其它外部生产者把结果放进了
+                //Scheduler，这一轮select已经起到了唤醒作用，排空即可，不当成协程的syscall
+                self.drain_waker();
+                continue;
+            }
             let token = event.token();
             self.scheduler.resume_syscall(token);
-            unsafe {
+            //`mark_readable`/`mark_writable`把就绪位记到当前挂着的`read_token`/`write_token`
+            //上，只有token匹配的等待者才会在`is_readable`/`is_writable`里看到它——避免恢复
+            //协程和它真正读/写完成之间丢失这次通知的同时，也不会被fd复用后的下一个等待者
+            //当成属于自己的伪就绪
+            let mut records = self.io_records.lock().unwrap();
+            if let Some(io) = records.get_mut(&fd) {
                 if event.is_readable() {
-                    assert!(READABLE_TOKEN_RECORDS.remove(&fd).is_some());
+                    io.mark_readable();
                 }
                 if event.is_writable() {
-                    assert!(WRITABLE_TOKEN_RECORDS.remove(&fd).is_some());
+                    io.mark_writable();
                 }
             }
         }
@@ -317,6 +620,13 @@ impl EventLoop {
         fd: libc::c_int,
         timeout: Option<Duration>,
     ) -> std::io::Result<()> {
+        if let Some(io) = self.io_records.lock().unwrap().get_mut(&fd) {
+            if io.is_readable() {
+                //缓存里已经是就绪的，直接恢复，不必再发起一次可能阻塞的select
+                io.clear_readable();
+                return Ok(());
+            }
+        }
         self.add_read_event(fd)?;
         self.wait_event(timeout)
     }
@@ -326,7 +636,79 @@ impl EventLoop {
         fd: libc::c_int,
         timeout: Option<Duration>,
     ) -> std::io::Result<()> {
+        if let Some(io) = self.io_records.lock().unwrap().get_mut(&fd) {
+            if io.is_writable() {
+                //缓存里已经是就绪的，直接恢复，不必再发起一次可能阻塞的select
+                io.clear_writable();
+                return Ok(());
+            }
+        }
         self.add_write_event(fd)?;
         self.wait_event(timeout)
     }
-}
\ No newline at end of file
+}
+
+impl Drop for EventLoop {
+    fn drop(&mut self) {
+        unsafe {
+            _ = libc::close(self.waker_fd);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leak_token(name: &str) -> usize {
+        let boxed: &'static String = Box::leak(Box::from(String::from(name)));
+        boxed as *const String as *const c_void as usize
+    }
+
+    //同一个fd上，读方向挂着一个协程、写方向挂着另一个协程时，取消读方向的协程只应该摘掉
+    //读方向的关注，不能把写方向那个协程还在等待的注册也一起删掉
+    #[test]
+    fn cancel_only_deregisters_matched_direction() -> std::io::Result<()> {
+        let event_loop = EventLoop::new(None)?;
+        let mut socks = [0 as libc::c_int; 2];
+        assert_eq!(
+            unsafe {
+                libc::socketpair(libc::AF_UNIX, libc::SOCK_STREAM, 0, socks.as_mut_ptr())
+            },
+            0
+        );
+        let fd = socks[0];
+
+        let reader = "cancel-test-reader";
+        let read_token = leak_token(reader);
+        let writer = "cancel-test-writer";
+        let write_token = leak_token(writer);
+
+        event_loop
+            .selector
+            .register(fd, read_token, Interest::READABLE)?;
+        event_loop
+            .selector
+            .register(fd, write_token, Interest::WRITABLE)?;
+        {
+            let mut records = event_loop.io_records.lock().unwrap();
+            let io = records.entry(fd).or_default();
+            io.set_read_token(Some(read_token));
+            io.set_write_token(Some(write_token));
+        }
+
+        event_loop.cancel(reader)?;
+
+        let records = event_loop.io_records.lock().unwrap();
+        let io = records.get(&fd).expect("write direction must stay registered");
+        assert_eq!(io.read_token(), None);
+        assert_eq!(io.write_token(), Some(write_token));
+        drop(records);
+
+        unsafe {
+            libc::close(socks[0]);
+            libc::close(socks[1]);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+/// 每个fd在某个`EventLoop`上的就绪状态缓存，参考了tokio的`ScheduledIo`设计。
+///
+/// 之前`READABLE_RECORDS`/`WRITABLE_RECORDS`/`*_TOKEN_RECORDS`是跨所有`EventLoop`共享的
+/// `static mut`，导致在loop A上`add_read_event`的fd可能被运行在loop B上的`del_event`误删，
+/// 且`select`在`add_read_event`和下一次`select`之间报告的就绪事件会被直接丢弃。现在每个
+/// `EventLoop`独立持有自己的fd状态，且在重新发起等待前先看一眼缓存的就绪位，命中时直接
+/// 恢复协程而不必再发起一次可能阻塞的syscall，从而不丢失边缘触发的通知。
+///
+/// 就绪位不再是单纯按fd存在的`bool`，而是记下它是为哪一个`read_token`/`write_token`置位的
+/// （`readable_for`/`writable_for`）：`wait`里`resume_syscall`唤醒的是token对应的那个协程，
+/// 而不是`wait_read_event`/`wait_write_event`的快路径，所以真正消费这个通知的应该是被
+/// `resume_syscall`叫醒的那个协程，而不是下一次`wait_read_event(fd)`撞上来的任意调用者——
+/// 后者如果fd已经被复用给了另一个协程，会把属于上一个等待者的就绪状态错当成自己的，凭空
+/// 产生一次不经过`select`的“伪就绪”。把就绪位绑定到置位时的token上，只要`set_read_token`/
+/// `set_write_token`换了新的等待者，旧的就绪位自然就不再匹配，无需额外清理。
+#[derive(Debug, Default, Copy, Clone)]
+pub(crate) struct ScheduledIo {
+    readable_for: Option<usize>,
+    writable_for: Option<usize>,
+    read_token: Option<usize>,
+    write_token: Option<usize>,
+}
+
+impl ScheduledIo {
+    //只有缓存的就绪位仍然是为当前挂着的`read_token`置位时才算数，fd被复用给新的等待者后
+    //（`set_read_token`换了新token）旧位自动失效
+    pub(crate) fn is_readable(&self) -> bool {
+        self.read_token.is_some() && self.readable_for == self.read_token
+    }
+
+    pub(crate) fn is_writable(&self) -> bool {
+        self.write_token.is_some() && self.writable_for == self.write_token
+    }
+
+    pub(crate) fn read_token(&self) -> Option<usize> {
+        self.read_token
+    }
+
+    pub(crate) fn write_token(&self) -> Option<usize> {
+        self.write_token
+    }
+
+    pub(crate) fn set_read_token(&mut self, token: Option<usize>) {
+        self.read_token = token;
+    }
+
+    pub(crate) fn set_write_token(&mut self, token: Option<usize>) {
+        self.write_token = token;
+    }
+
+    //记下这次就绪是为当前的`read_token`置位的，而不是简单地OR一个bit：这样只有当前挂着
+    //的等待者才能在`is_readable`里看到它，换了等待者之后会自然失效
+    pub(crate) fn mark_readable(&mut self) {
+        self.readable_for = self.read_token;
+    }
+
+    pub(crate) fn mark_writable(&mut self) {
+        self.writable_for = self.write_token;
+    }
+
+    //只清除被恢复的协程实际消费掉的位，不影响另一方向缓存的就绪状态
+    pub(crate) fn clear_readable(&mut self) {
+        self.readable_for = None;
+    }
+
+    pub(crate) fn clear_writable(&mut self) {
+        self.writable_for = None;
+    }
+}
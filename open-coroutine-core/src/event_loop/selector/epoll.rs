@@ -0,0 +1,155 @@
+use crate::event_loop::event::Events;
+use crate::event_loop::interest::Interest;
+use crate::event_loop::selector::Selector;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+//一个fd上读写两个方向各自的token，读写可能分别挂着不同的协程，必须分开记录，否则
+//第二次`register`会覆盖第一次存下的token，`select`也就只能恢复其中一个等待者
+#[derive(Debug, Default, Copy, Clone)]
+struct FdTokens {
+    read: Option<usize>,
+    write: Option<usize>,
+}
+
+impl FdTokens {
+    fn interests(&self) -> Interest {
+        let mut interests = Interest::NONE;
+        if self.read.is_some() {
+            interests |= Interest::READABLE;
+        }
+        if self.write.is_some() {
+            interests |= Interest::WRITABLE;
+        }
+        interests
+    }
+}
+
+/// 默认后端：基于epoll(7)，edge-triggered。epoll的`data.u64`只有一个字段，这里用它带回
+/// fd本身，读写各自的token则单独维护一张fd -> `FdTokens`的表，在`select`翻译事件时查回来。
+#[derive(Debug)]
+pub(crate) struct EpollSelector {
+    epoll_fd: libc::c_int,
+    tokens: Mutex<HashMap<libc::c_int, FdTokens>>,
+}
+
+impl EpollSelector {
+    pub(crate) fn new() -> std::io::Result<Self> {
+        let epoll_fd = unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) };
+        if epoll_fd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(EpollSelector {
+            epoll_fd,
+            tokens: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn ctl(&self, op: libc::c_int, fd: libc::c_int, interests: Interest) -> std::io::Result<()> {
+        let mut event = libc::epoll_event {
+            events: interests.to_epoll_events(),
+            u64: fd as u64,
+        };
+        let ret = unsafe { libc::epoll_ctl(self.epoll_fd, op, fd, &mut event) };
+        if ret < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+impl Selector for EpollSelector {
+    fn register(&self, fd: libc::c_int, token: usize, interests: Interest) -> std::io::Result<()> {
+        let mut tokens = self.tokens.lock().unwrap();
+        let entry = tokens.entry(fd).or_default();
+        //fd已经注册过（比如一个协程先挂了读，另一个再挂写）时，epoll不允许再来一次
+        //EPOLL_CTL_ADD（会返回EEXIST），这里改成把两个方向的interest合并后MOD，而不是
+        //覆盖掉另一个方向已经存下的token
+        let already_registered = entry.read.is_some() || entry.write.is_some();
+        if interests.is_readable() {
+            entry.read = Some(token);
+        }
+        if interests.is_writable() {
+            entry.write = Some(token);
+        }
+        let merged = entry.interests();
+        let op = if already_registered {
+            libc::EPOLL_CTL_MOD
+        } else {
+            libc::EPOLL_CTL_ADD
+        };
+        self.ctl(op, fd, merged)
+    }
+
+    fn reregister(
+        &self,
+        fd: libc::c_int,
+        token: usize,
+        interests: Interest,
+    ) -> std::io::Result<()> {
+        //和`register`的合并语义不同，`reregister`用于某个方向被摘掉之后把fd收窄到只剩
+        //另一个方向，所以这里直接把两个方向的token都按`interests`重设，而不是合并
+        let mut tokens = self.tokens.lock().unwrap();
+        let entry = tokens.entry(fd).or_default();
+        entry.read = interests.is_readable().then_some(token);
+        entry.write = interests.is_writable().then_some(token);
+        self.ctl(libc::EPOLL_CTL_MOD, fd, interests)
+    }
+
+    fn deregister(&self, fd: libc::c_int) -> std::io::Result<()> {
+        let ret = unsafe {
+            libc::epoll_ctl(self.epoll_fd, libc::EPOLL_CTL_DEL, fd, std::ptr::null_mut())
+        };
+        if ret < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        _ = self.tokens.lock().unwrap().remove(&fd);
+        Ok(())
+    }
+
+    fn select(&self, events: &mut Events, timeout: Option<Duration>) -> std::io::Result<()> {
+        let timeout_millis = timeout.map_or(-1, |d| d.as_millis().min(i32::MAX as u128) as i32);
+        events.clear();
+        let mut raw = vec![libc::epoll_event { events: 0, u64: 0 }; events.capacity()];
+        let n = unsafe {
+            libc::epoll_wait(
+                self.epoll_fd,
+                raw.as_mut_ptr(),
+                raw.len() as libc::c_int,
+                timeout_millis,
+            )
+        };
+        if n < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let tokens = self.tokens.lock().unwrap();
+        for raw_event in raw.into_iter().take(n as usize) {
+            let fd = raw_event.u64 as libc::c_int;
+            let Some(entry) = tokens.get(&fd) else {
+                continue;
+            };
+            //读写各自持有不同token的时候，一次epoll_wait命中的fd要分别上报成两条事件，
+            //不能合并成一条，否则只有其中一个等待者会被恢复
+            if raw_event.events & libc::EPOLLIN as u32 != 0 {
+                if let Some(token) = entry.read {
+                    events.push(fd, token, Interest::READABLE);
+                }
+            }
+            if raw_event.events & libc::EPOLLOUT as u32 != 0 {
+                if let Some(token) = entry.write {
+                    events.push(fd, token, Interest::WRITABLE);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for EpollSelector {
+    fn drop(&mut self) {
+        unsafe {
+            _ = libc::close(self.epoll_fd);
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use crate::event_loop::event::Events;
+use crate::event_loop::interest::Interest;
+use std::fmt::Debug;
+use std::time::Duration;
+
+#[cfg(feature = "poll-selector")]
+mod poll;
+
+#[cfg(not(feature = "poll-selector"))]
+mod epoll;
+
+#[cfg(feature = "poll-selector")]
+use poll::PollSelector as DefaultImpl;
+
+#[cfg(not(feature = "poll-selector"))]
+use epoll::EpollSelector as DefaultImpl;
+
+/// 探测fd就绪状态的后端抽象。之前`EventLoop`直接拥有一个具体的`Selector`结构体，写死了
+/// 只能用epoll；现在`EventLoop`只依赖这个trait。未开启`poll-selector`特性时一律使用epoll
+/// 后端，开启后换成一个更便携、更易审计的`poll(2)`实现（例如用于权限受限的容器）。这里没有
+/// kqueue后端，也不会在目标平台缺少epoll时自动改用`poll(2)`。
+pub(crate) trait Selector: Debug + Send + Sync {
+    fn register(&self, fd: libc::c_int, token: usize, interests: Interest) -> std::io::Result<()>;
+
+    fn reregister(&self, fd: libc::c_int, token: usize, interests: Interest)
+        -> std::io::Result<()>;
+
+    fn deregister(&self, fd: libc::c_int) -> std::io::Result<()>;
+
+    fn select(&self, events: &mut Events, timeout: Option<Duration>) -> std::io::Result<()>;
+}
+
+/// 当前平台/feature组合下选中的默认后端，`EventLoop`通过它构造`Box<dyn Selector>`。
+#[derive(Debug)]
+pub(crate) struct DefaultSelector(DefaultImpl);
+
+impl DefaultSelector {
+    pub(crate) fn new() -> std::io::Result<Box<dyn Selector>> {
+        Ok(Box::new(DefaultSelector(DefaultImpl::new()?)))
+    }
+}
+
+impl Selector for DefaultSelector {
+    fn register(&self, fd: libc::c_int, token: usize, interests: Interest) -> std::io::Result<()> {
+        self.0.register(fd, token, interests)
+    }
+
+    fn reregister(
+        &self,
+        fd: libc::c_int,
+        token: usize,
+        interests: Interest,
+    ) -> std::io::Result<()> {
+        self.0.reregister(fd, token, interests)
+    }
+
+    fn deregister(&self, fd: libc::c_int) -> std::io::Result<()> {
+        self.0.deregister(fd)
+    }
+
+    fn select(&self, events: &mut Events, timeout: Option<Duration>) -> std::io::Result<()> {
+        self.0.select(events, timeout)
+    }
+}
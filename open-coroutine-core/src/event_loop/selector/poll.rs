@@ -0,0 +1,147 @@
+use crate::event_loop::event::Events;
+use crate::event_loop::interest::Interest;
+use crate::event_loop::selector::Selector;
+use std::sync::Mutex;
+use std::time::Duration;
+
+//一个fd上读写两个方向各自的token，读写可能分别挂着不同的协程，必须分开记录，否则
+//第二次`register`会覆盖第一次存下的token，`select`也就只能恢复其中一个等待者
+#[derive(Debug, Default, Copy, Clone)]
+struct FdTokens {
+    read: Option<usize>,
+    write: Option<usize>,
+}
+
+/// 基于`poll(2)`的便携后端，behind the `poll-selector` feature。灵感来自popol之类的极简
+/// 封装：不依赖epoll/kqueue，只用一个`pollfd`数组加一个平行的token数组，换来可以在缺少
+/// epoll的平台（比如受限的容器）上跑起来的简单、易审计的实现，代价是`register`/
+/// `deregister`是O(n)而不是O(1)。
+#[derive(Debug)]
+pub(crate) struct PollSelector {
+    inner: Mutex<PollState>,
+}
+
+#[derive(Debug, Default)]
+struct PollState {
+    fds: Vec<libc::pollfd>,
+    tokens: Vec<FdTokens>,
+}
+
+impl PollState {
+    fn position(&self, fd: libc::c_int) -> Option<usize> {
+        self.fds.iter().position(|pfd| pfd.fd == fd)
+    }
+}
+
+impl PollSelector {
+    pub(crate) fn new() -> std::io::Result<Self> {
+        Ok(PollSelector {
+            inner: Mutex::new(PollState::default()),
+        })
+    }
+}
+
+impl Selector for PollSelector {
+    fn register(&self, fd: libc::c_int, token: usize, interests: Interest) -> std::io::Result<()> {
+        let mut state = self.inner.lock().unwrap();
+        match state.position(fd) {
+            //fd已经注册过（比如一个协程先挂了读，另一个再挂写），把新方向的interest合并
+            //进已有的`pollfd.events`，而不是像之前那样直接报`AlreadyExists`拒绝第二个方向
+            Some(index) => {
+                if interests.is_readable() {
+                    state.tokens[index].read = Some(token);
+                }
+                if interests.is_writable() {
+                    state.tokens[index].write = Some(token);
+                }
+                state.fds[index].events |= interests.to_poll_events();
+            }
+            None => {
+                let mut tokens = FdTokens::default();
+                if interests.is_readable() {
+                    tokens.read = Some(token);
+                }
+                if interests.is_writable() {
+                    tokens.write = Some(token);
+                }
+                state.fds.push(libc::pollfd {
+                    fd,
+                    events: interests.to_poll_events(),
+                    revents: 0,
+                });
+                state.tokens.push(tokens);
+            }
+        }
+        Ok(())
+    }
+
+    fn reregister(
+        &self,
+        fd: libc::c_int,
+        token: usize,
+        interests: Interest,
+    ) -> std::io::Result<()> {
+        //和`register`的合并语义不同，`reregister`用于某个方向被摘掉之后把fd收窄到只剩
+        //另一个方向，所以这里直接把两个方向的token都按`interests`重设，而不是合并
+        let mut state = self.inner.lock().unwrap();
+        let index = state.position(fd).ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "fd not registered")
+        })?;
+        state.tokens[index] = FdTokens {
+            read: interests.is_readable().then_some(token),
+            write: interests.is_writable().then_some(token),
+        };
+        state.fds[index].events = interests.to_poll_events();
+        Ok(())
+    }
+
+    fn deregister(&self, fd: libc::c_int) -> std::io::Result<()> {
+        let mut state = self.inner.lock().unwrap();
+        let index = state.position(fd).ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "fd not registered")
+        })?;
+        state.fds.swap_remove(index);
+        state.tokens.swap_remove(index);
+        Ok(())
+    }
+
+    fn select(&self, events: &mut Events, timeout: Option<Duration>) -> std::io::Result<()> {
+        let timeout_millis = timeout.map_or(-1, |d| d.as_millis().min(i32::MAX as u128) as i32);
+        let mut state = self.inner.lock().unwrap();
+        let ready = unsafe {
+            libc::poll(
+                state.fds.as_mut_ptr(),
+                state.fds.len() as libc::nfds_t,
+                timeout_millis,
+            )
+        };
+        if ready < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        events.clear();
+        if ready == 0 {
+            return Ok(());
+        }
+        for (pfd, tokens) in state.fds.iter().zip(state.tokens.iter()) {
+            if pfd.revents == 0 {
+                continue;
+            }
+            //读写各自持有不同token的时候，一次poll命中的fd要分别上报成两条事件，不能
+            //合并成一条，否则只有其中一个等待者会被恢复
+            if pfd.revents & (libc::POLLIN | libc::POLLHUP | libc::POLLERR) != 0 {
+                if let Some(token) = tokens.read {
+                    events.push(pfd.fd, token, Interest::READABLE);
+                }
+            }
+            if pfd.revents & (libc::POLLOUT | libc::POLLHUP | libc::POLLERR) != 0 {
+                if let Some(token) = tokens.write {
+                    events.push(pfd.fd, token, Interest::WRITABLE);
+                }
+            }
+        }
+        for pfd in &mut state.fds {
+            pfd.revents = 0;
+        }
+        Ok(())
+    }
+}